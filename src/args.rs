@@ -0,0 +1,29 @@
+pub const USAGE: &str = "usage: shell [-i] [-n] [--posix] [--version] [--norc] [--noprofile]";
+
+#[derive(Default)]
+pub struct ShellArgs {
+    pub interactive: Option<bool>,
+    pub noexec: bool,
+    pub posix: bool,
+    pub version: bool,
+}
+
+impl ShellArgs {
+    pub fn parse(args: impl Iterator<Item = String>) -> anyhow::Result<ShellArgs> {
+        let mut parsed = ShellArgs::default();
+
+        for arg in args {
+            match arg.as_ref() {
+                "-i" => parsed.interactive = Some(true),
+                "-n" => parsed.noexec = true,
+                "--posix" => parsed.posix = true,
+                "--version" => parsed.version = true,
+                // no startup file is read yet, so there is nothing to skip
+                "--norc" | "--noprofile" => {}
+                _ => anyhow::bail!("{}: invalid option", arg),
+            }
+        }
+
+        Ok(parsed)
+    }
+}
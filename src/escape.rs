@@ -0,0 +1,109 @@
+use std::{iter::Peekable, str::Chars};
+
+/// Decodes the backslash escapes understood by `echo -e`.
+///
+/// Returns the decoded text and whether a `\c` was seen, in which case everything after it
+/// (including `echo`'s trailing newline) must be dropped.
+pub fn decode_echo(value: &str) -> (String, bool) {
+    let mut decoded = String::new();
+    let mut chars = value.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            decoded.push(ch);
+            continue;
+        }
+
+        match chars.next() {
+            Some('c') => return (decoded, true),
+            Some('0') => decoded.push(take_code(&mut chars, 8, 3, String::new()).unwrap_or('\0')),
+            Some(next) => push_common_escape(&mut decoded, next, &mut chars),
+            None => decoded.push('\\'),
+        }
+    }
+
+    (decoded, false)
+}
+
+/// Decodes the body of an ANSI-C quoted `$'...'` word.
+pub fn decode_ansi_c(value: &str) -> String {
+    let mut decoded = String::new();
+    let mut chars = value.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            decoded.push(ch);
+            continue;
+        }
+
+        match chars.next() {
+            Some(digit @ '0'..='7') => {
+                decoded.push(take_code(&mut chars, 8, 3, digit.to_string()).unwrap_or('\0'))
+            }
+            Some(quote @ ('\'' | '"' | '?')) => decoded.push(quote),
+            // `\cx` is the control character for `x`, e.g. `\cA` is 0x01
+            Some('c') => match chars.next() {
+                Some(ctrl) => decoded.push(char::from(ctrl as u8 & 0x1f)),
+                None => decoded.push_str("\\c"),
+            },
+            Some(next) => push_common_escape(&mut decoded, next, &mut chars),
+            None => decoded.push('\\'),
+        }
+    }
+
+    decoded
+}
+
+/// Decodes an escape shared by `echo -e` and `$'...'` given the character after the backslash,
+/// keeping it literally if it isn't one.
+fn push_common_escape(decoded: &mut String, ch: char, chars: &mut Peekable<Chars>) {
+    let escaped = match ch {
+        'x' => take_code(chars, 16, 2, String::new()),
+        'u' => take_code(chars, 16, 4, String::new()),
+        'U' => take_code(chars, 16, 8, String::new()),
+        _ => simple_escape(ch),
+    };
+
+    match escaped {
+        Some(escaped) => decoded.push(escaped),
+        None => {
+            decoded.push('\\');
+            decoded.push(ch);
+        }
+    }
+}
+
+fn simple_escape(ch: char) -> Option<char> {
+    match ch {
+        'a' => Some('\x07'),
+        'b' => Some('\x08'),
+        'e' | 'E' => Some('\x1b'),
+        'f' => Some('\x0c'),
+        'n' => Some('\n'),
+        'r' => Some('\r'),
+        't' => Some('\t'),
+        'v' => Some('\x0b'),
+        '\\' => Some('\\'),
+        _ => None,
+    }
+}
+
+/// Reads digits in `radix` onto `digits` until it holds `max_digits` of them, and returns the
+/// character they encode, or `None` if there were no digits at all.
+fn take_code(
+    chars: &mut Peekable<Chars>,
+    radix: u32,
+    max_digits: usize,
+    mut digits: String,
+) -> Option<char> {
+    while digits.len() < max_digits {
+        match chars.next_if(|x| x.is_digit(radix)) {
+            Some(digit) => digits.push(digit),
+            None => break,
+        }
+    }
+
+    u32::from_str_radix(&digits, radix)
+        .ok()
+        .map(|code| char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER))
+}
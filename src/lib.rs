@@ -0,0 +1,1261 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    env, fmt,
+    fs::{self, File, OpenOptions},
+    iter::Peekable,
+    marker::PhantomData,
+    os::{fd::AsFd, unix::process::CommandExt},
+    path::{Component, Path, PathBuf},
+    process::{self, Stdio},
+    str::Chars,
+};
+
+use strum::VariantArray;
+use strum_macros::{EnumDiscriminants, VariantArray};
+
+mod escape;
+mod options;
+mod redirection;
+mod suggest;
+mod ulimit;
+
+pub use options::ShellOptions;
+pub use redirection::{Redirection, RedirectionMode, RedirectionSource, RedirectionTarget};
+
+struct LineTokenIter<'a> {
+    chars: Peekable<Chars<'a>>,
+    /// Each redirection's operator, e.g. `2>>`, and its target word.
+    redirections: Vec<(String, String)>,
+    /// Set when tokenizing stops early because the line is malformed.
+    error: Option<ParseError>,
+}
+
+impl<'a> LineTokenIter<'a> {
+    pub fn new(line: &'a str) -> Self {
+        LineTokenIter {
+            chars: line.chars().peekable(),
+            redirections: vec![],
+            error: None,
+        }
+    }
+
+    /// Records why the line can't be parsed, keeping the first reason if there are several, and
+    /// ends the current token.
+    fn fail(&mut self, err: ParseError) -> Option<String> {
+        self.error.get_or_insert(err);
+        None
+    }
+
+    /// Reads the rest of a redirection whose `>` has just been consumed, up to and including
+    /// its target word, so tokenizing can carry on after it.
+    fn read_redirection(&mut self, mut operator: String) {
+        operator.push('>');
+        if let Some(ch) = self.chars.next_if_eq(&'>') {
+            operator.push(ch);
+        }
+        if let Some(ch) = self.chars.next_if_eq(&'&') {
+            operator.push(ch);
+        }
+
+        match self.next() {
+            Some(target) => self.redirections.push((operator, target)),
+            None => {
+                self.fail(ParseError::MissingRedirectionTarget);
+            }
+        }
+    }
+
+    /// Reads the body of a `$'...'` word whose opening `$'` has just been consumed, decoding
+    /// its escapes.
+    fn read_ansi_c_quote(&mut self) -> Option<String> {
+        let mut raw = String::new();
+        loop {
+            match self.chars.next()? {
+                '\'' => break,
+                '\\' => {
+                    // keep escapes intact so an escaped quote doesn't end the word
+                    raw.push('\\');
+                    raw.extend(self.chars.next());
+                }
+                ch => raw.push(ch),
+            }
+        }
+
+        Some(escape::decode_ansi_c(&raw))
+    }
+
+    fn redirections(&self) -> Result<Vec<Redirection>, ParseError> {
+        self.redirections
+            .iter()
+            .map(|(operator, target)| {
+                Redirection::parse(operator, target.clone())
+                    .ok_or_else(|| ParseError::BadRedirection(format!("{}{}", operator, target)))
+            })
+            .collect()
+    }
+}
+
+enum QuoteKind {
+    Single,
+    Double,
+    None,
+}
+
+impl<'a> Iterator for LineTokenIter<'a> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut token = String::new();
+        let mut quote = QuoteKind::None;
+        // a quoted word is a token even if it's empty, e.g. `echo "" x`
+        let mut quoted = false;
+
+        while let Some(ch) = self.chars.next() {
+            match (ch, &quote) {
+                ('"', QuoteKind::Double) => quote = QuoteKind::None,
+                ('"', QuoteKind::None) => {
+                    quote = QuoteKind::Double;
+                    quoted = true;
+                }
+                ('\'', QuoteKind::Single) => quote = QuoteKind::None,
+                ('\'', QuoteKind::None) => {
+                    quote = QuoteKind::Single;
+                    quoted = true;
+                }
+                ('$', QuoteKind::None) if self.chars.next_if_eq(&'\'').is_some() => {
+                    match self.read_ansi_c_quote() {
+                        Some(decoded) => token.push_str(&decoded),
+                        None => return self.fail(ParseError::UnterminatedQuote('\'')),
+                    }
+                    quoted = true;
+                }
+                ('\\', QuoteKind::None) => match self.chars.next() {
+                    Some(next) => token.push(next),
+                    None => return self.fail(ParseError::TrailingBackslash),
+                },
+                ('\\', QuoteKind::Double) => match self.chars.peek() {
+                    Some(next) => {
+                        if matches!(next, '\\' | '$' | '"' | '\n') {
+                            token.push(*next);
+                            self.chars.next().unwrap();
+                        } else {
+                            token.push('\\');
+                        }
+                    }
+                    None => return self.fail(ParseError::TrailingBackslash),
+                },
+                (ch, QuoteKind::None) if ch.is_whitespace() => {
+                    if !token.is_empty() || quoted {
+                        break;
+                    }
+                }
+                ('>', QuoteKind::None) => {
+                    if !quoted && (token.chars().all(|x| x.is_ascii_digit()) || token == "&") {
+                        // the token so far names the redirected stream, e.g. `2>` or `&>`
+                        let source = std::mem::take(&mut token);
+                        self.read_redirection(source);
+                    } else {
+                        self.read_redirection(String::new());
+                        break;
+                    }
+                }
+                _ => token.push(ch),
+            }
+        }
+
+        match quote {
+            QuoteKind::Single => return self.fail(ParseError::UnterminatedQuote('\'')),
+            QuoteKind::Double => return self.fail(ParseError::UnterminatedQuote('"')),
+            QuoteKind::None => {}
+        }
+
+        if !token.is_empty() || quoted {
+            Some(token)
+        } else {
+            None
+        }
+    }
+}
+
+/// Options `set` knows about. Only `-n` (and `-o posix`) is implemented; the rest are rejected as
+/// not supported rather than as invalid.
+const SET_FLAGS: &str = "abefhkmnptuvxBCEHPT";
+
+#[derive(EnumDiscriminants)]
+#[strum_discriminants(derive(VariantArray))]
+enum Command {
+    Exit(i32),
+    Builtin(String, Vec<String>),
+    Echo(Vec<String>),
+    Type(Vec<String>),
+    Pwd,
+    Cd(CdMode, Option<PathBuf>),
+    Shopt(ShoptOptions, Vec<String>),
+    Set(SetOptions),
+    Ulimit(UlimitOptions),
+    NotFound(String, Vec<String>),
+}
+
+impl Command {
+    fn parse(name: String, rest: Vec<String>) -> anyhow::Result<Command> {
+        let command = match name.as_ref() {
+            "exit" => {
+                let code = match rest.len() {
+                    0 => 127,
+                    1 => rest[0].parse::<i64>()?,
+                    _ => anyhow::bail!("Too many arguments (expected 2"),
+                };
+
+                // a parent only ever sees the low byte, so `exit 256` is 0 and `exit -1` is 255
+                Command::Exit((code & 0xFF) as i32)
+            }
+            "builtin" => {
+                let mut rest = rest.into_iter();
+                Command::Builtin(rest.next().unwrap_or_default(), rest.collect())
+            }
+            "echo" => Command::Echo(rest),
+            "type" => Command::Type(rest),
+            "pwd" => {
+                if !rest.is_empty() {
+                    anyhow::bail!("pwd: expected 0 arguments; got {}", rest.len());
+                }
+
+                Command::Pwd
+            }
+            "cd" => {
+                let mut mode = CdMode::Logical;
+                let mut args = rest.iter().peekable();
+                while let Some(option) = args.next_if(|x| *x == "-L" || *x == "-P") {
+                    mode = if option == "-P" {
+                        CdMode::Physical
+                    } else {
+                        CdMode::Logical
+                    };
+                }
+
+                let args = args.collect::<Vec<_>>();
+                let path = if args.is_empty() {
+                    None
+                } else if args.len() == 1 {
+                    Some(PathBuf::from(args[0]))
+                } else {
+                    anyhow::bail!("Too many arguments for cd command")
+                };
+
+                Command::Cd(mode, path)
+            }
+            "shopt" => {
+                let (options, names) = ShoptOptions::parse(rest)?;
+                Command::Shopt(options, names)
+            }
+            "set" => {
+                let mut set = SetOptions::default();
+                let mut args = rest.iter();
+                while let Some(arg) = args.next() {
+                    // there are no positional parameters to set, so those are ignored
+                    let on = match arg.chars().next() {
+                        _ if arg == "--" => break,
+                        Some('-') => true,
+                        Some('+') => false,
+                        _ => break,
+                    };
+
+                    for flag in arg[1..].chars() {
+                        match flag {
+                            'n' => set.noexec = Some(on),
+                            // `-o name` takes the option's long name as the next argument
+                            'o' => match args.next().map(String::as_str) {
+                                Some("noexec") => set.noexec = Some(on),
+                                Some("posix") => set.posix = Some(on),
+                                Some(name) => {
+                                    anyhow::bail!("set: {}o {}: not supported", &arg[..1], name)
+                                }
+                                None => anyhow::bail!(
+                                    "set: {}o: option requires an argument",
+                                    &arg[..1]
+                                ),
+                            },
+                            _ if SET_FLAGS.contains(flag) => {
+                                anyhow::bail!("set: {}{}: not supported", &arg[..1], flag)
+                            }
+                            _ => anyhow::bail!("set: {}{}: invalid option", &arg[..1], flag),
+                        }
+                    }
+                }
+
+                Command::Set(set)
+            }
+            "ulimit" => Command::Ulimit(UlimitOptions::parse(rest)?),
+            _ => Command::NotFound(name, rest),
+        };
+
+        Ok(command)
+    }
+
+    /// Runs the command, returning the exit code if it asked the shell to exit.
+    fn execute(
+        self,
+        paths: &EnvPaths,
+        options: &mut ShellOptions,
+        stdout: &mut impl Write,
+        stderr: &mut impl Write,
+    ) -> Option<i32> {
+        let name = self.name();
+        match self.run(paths, options, stdout, stderr) {
+            Ok(exit) => exit,
+            Err(err) => {
+                // if it's stderr that can't be written to, this is lost too
+                let _ = writeln!(stderr, "{}: write error: {}", name, io_reason(&err));
+                None
+            }
+        }
+    }
+
+    /// The name the command was run as.
+    fn name(&self) -> String {
+        match self {
+            Command::NotFound(name, _) => name.clone(),
+            _ => CommandDiscriminants::from(self)
+                .builtin_name()
+                .unwrap_or_default()
+                .to_string(),
+        }
+    }
+
+    /// Does the work of [`Command::execute`], failing if the output can't be written.
+    fn run(
+        self,
+        paths: &EnvPaths,
+        options: &mut ShellOptions,
+        stdout: &mut impl Write,
+        stderr: &mut impl Write,
+    ) -> io::Result<Option<i32>> {
+        match self {
+            Command::Exit(code) => return Ok(Some(code)),
+            Command::Builtin(name, args) => {
+                if name.is_empty() {
+                    return Ok(None);
+                }
+
+                if !CommandDiscriminants::is_builtin(&name) {
+                    writeln!(stderr, "builtin: {}: not a shell builtin", name)?;
+                    return Ok(None);
+                }
+
+                match Command::parse(name, args) {
+                    Ok(command) => return Ok(command.execute(paths, options, stdout, stderr)),
+                    Err(err) => writeln!(stderr, "{}", err)?,
+                }
+            }
+            Command::Echo(vec) => {
+                let (echo, vec) = if options.posix {
+                    (EchoOptions::default(), vec)
+                } else {
+                    EchoOptions::parse(vec)
+                };
+
+                let mut message = vec.join(" ");
+                let mut newline = echo.newline;
+
+                if echo.escapes {
+                    let (decoded, stop) = escape::decode_echo(&message);
+                    message = decoded;
+                    newline &= !stop;
+                }
+
+                write!(stdout, "{}", message)?;
+
+                if newline {
+                    writeln!(stdout)?;
+                }
+            }
+            Command::Type(vec) => {
+                for name in &vec {
+                    if CommandDiscriminants::is_builtin(name) {
+                        writeln!(stdout, "{} is a shell builtin", name)?;
+                    } else {
+                        match paths.expand(name) {
+                            Some(path) => writeln!(stdout, "{} is {}", name, path.display())?,
+                            _ => writeln!(stderr, "{}: not found", name)?,
+                        }
+                    }
+                }
+            }
+            Command::Pwd => match logical_current_dir() {
+                Ok(dir) => writeln!(stdout, "{}", dir.display())?,
+                Err(err) => writeln!(stderr, "pwd: {}", err)?,
+            },
+            Command::Cd(mode, path) => {
+                // a bare `cd` does nothing yet
+                let Some(mut path) = path else {
+                    return Ok(None);
+                };
+
+                if path.to_str() == Some("~") {
+                    match env::var("HOME") {
+                        Ok(home_dir) => path = PathBuf::from(home_dir),
+                        _ => {
+                            writeln!(stderr, "cd: ~: home dir is not available")?;
+                            return Ok(None);
+                        }
+                    };
+                }
+
+                let mut target = match (&mode, logical_current_dir()) {
+                    (CdMode::Logical, Ok(cwd)) => normalize_lexically(&cwd.join(&path)),
+                    _ => path.clone(),
+                };
+
+                // a dangling symlink isn't a typo, so it's left for `set_current_dir` to reject
+                if fs::symlink_metadata(&target).is_err() {
+                    let corrected = if options.cdspell && options.interactive {
+                        spell_correct(&target)
+                    } else {
+                        None
+                    };
+
+                    let Some(corrected) = corrected else {
+                        let shown = clean_path(&path);
+                        writeln!(stderr, "cd: {}: No such file or directory", shown.display())?;
+                        return Ok(None);
+                    };
+
+                    writeln!(stdout, "{}", corrected.display())?;
+                    target = corrected;
+                }
+
+                if let Err(err) = env::set_current_dir(&target) {
+                    let shown = clean_path(&path);
+                    writeln!(stderr, "cd: {}: {}", shown.display(), io_reason(&err))?;
+                    return Ok(None);
+                }
+
+                let pwd = match mode {
+                    CdMode::Logical => target,
+                    CdMode::Physical => env::current_dir().unwrap(),
+                };
+                env::set_var("PWD", pwd);
+            }
+            Command::Set(set) => {
+                if let Some(noexec) = set.noexec {
+                    options.noexec = noexec;
+                }
+                if let Some(posix) = set.posix {
+                    options.posix = posix;
+                }
+            }
+            Command::Shopt(shopt, names) => {
+                let listing = names.is_empty();
+                let names = if listing {
+                    ShellOptions::SHOPT_NAMES
+                        .iter()
+                        .map(|x| x.to_string())
+                        .collect()
+                } else {
+                    names
+                };
+
+                for name in names {
+                    let Some(value) = options.shopt(&name) else {
+                        writeln!(stderr, "shopt: {}: invalid shell option name", name)?;
+                        continue;
+                    };
+
+                    // without names, `-s`/`-u` list just the options that are on/off
+                    match shopt.set {
+                        Some(set) if !listing => *value = set,
+                        Some(set) if set != *value => {}
+                        _ if shopt.quiet => {}
+                        _ => {
+                            let state = if *value { "on" } else { "off" };
+                            writeln!(stdout, "{:<15}\t{}", name, state)?;
+                        }
+                    }
+                }
+            }
+            Command::Ulimit(ulimit) => {
+                let limits = if ulimit.all {
+                    ulimit::LIMITS.iter().collect()
+                } else {
+                    ulimit.limits
+                };
+                // like bash, changing a limit without -H or -S changes both
+                let (soft, hard) = if ulimit.soft || ulimit.hard {
+                    (ulimit.soft, ulimit.hard)
+                } else {
+                    (true, true)
+                };
+                let labelled = limits.len() > 1;
+
+                for limit in limits {
+                    let Some(value) = ulimit.value else {
+                        match limit.show(hard && !soft) {
+                            Ok(value) if labelled => {
+                                writeln!(stdout, "{}{}", limit.label(), value)?
+                            }
+                            Ok(value) => writeln!(stdout, "{}", value)?,
+                            Err(err) => writeln!(
+                                stderr,
+                                "ulimit: {}: cannot get limit: {}",
+                                limit.description,
+                                io_reason(&err)
+                            )?,
+                        }
+                        continue;
+                    };
+
+                    if let Err(err) = limit.set(value, soft, hard) {
+                        writeln!(
+                            stderr,
+                            "ulimit: {}: cannot modify limit: {}",
+                            limit.description,
+                            io_reason(&err)
+                        )?;
+                    }
+                }
+            }
+            Command::NotFound(cmd, args) => match paths.expand(&cmd) {
+                Some(path) => {
+                    // children see the name that was typed as argv[0], not the resolved path
+                    let Ok(output) = process::Command::new(&path)
+                        .arg0(&cmd)
+                        .args(args)
+                        .stdin(Stdio::inherit())
+                        .output()
+                    else {
+                        writeln!(stderr, "{}: Failed to execute command", path.display())?;
+                        return Ok(None);
+                    };
+
+                    // `write` may stop short, which would silently drop the rest of the output
+                    // copy both even if one fails, so a child's errors still show with stdout closed
+                    let copied = stdout.write_all(&output.stdout);
+                    stderr.write_all(&output.stderr).and(copied)?;
+                }
+                None if options.interactive => {
+                    // suggestions are for whoever is typing; scripts keep the plain message
+                    let path_names = paths.command_names();
+                    let builtin_names = CommandDiscriminants::VARIANTS
+                        .iter()
+                        .filter_map(|x| x.builtin_name());
+                    let candidates = builtin_names.chain(path_names.iter().map(|x| x.as_str()));
+
+                    match suggest::closest(&cmd, candidates) {
+                        Some(suggestion) => writeln!(
+                            stderr,
+                            "{}: command not found. Did you mean '{}'?",
+                            cmd, suggestion
+                        )?,
+                        None => writeln!(stderr, "{}: command not found", cmd)?,
+                    }
+                }
+                None => writeln!(stderr, "{}: command not found", cmd)?,
+            },
+        }
+
+        Ok(None)
+    }
+}
+
+impl CommandDiscriminants {
+    fn builtin_name(&self) -> Option<&'static str> {
+        match self {
+            CommandDiscriminants::Exit => Some("exit"),
+            CommandDiscriminants::Builtin => Some("builtin"),
+            CommandDiscriminants::Echo => Some("echo"),
+            CommandDiscriminants::Type => Some("type"),
+            CommandDiscriminants::Pwd => Some("pwd"),
+            CommandDiscriminants::Cd => Some("cd"),
+            CommandDiscriminants::Shopt => Some("shopt"),
+            CommandDiscriminants::Set => Some("set"),
+            CommandDiscriminants::Ulimit => Some("ulimit"),
+            CommandDiscriminants::NotFound => None,
+        }
+    }
+
+    pub fn is_builtin(command: &str) -> bool {
+        CommandDiscriminants::VARIANTS
+            .iter()
+            .any(|x| x.builtin_name().map(|x| x == command).unwrap_or(false))
+    }
+}
+
+enum CdMode {
+    /// Follow the path as written, so `..` undoes the previous component even across symlinks.
+    Logical,
+    /// Let the OS resolve the path, as `cd -P` does.
+    Physical,
+}
+
+struct EchoOptions {
+    newline: bool,
+    escapes: bool,
+}
+
+impl Default for EchoOptions {
+    fn default() -> Self {
+        EchoOptions {
+            newline: true,
+            escapes: false,
+        }
+    }
+}
+
+impl EchoOptions {
+    /// Splits the leading options off `echo`'s arguments.
+    ///
+    /// An argument is an option only if it is a `-` followed by one or more of `n`, `e` and
+    /// `E`; scanning stops at the first argument that isn't, so `echo -n -x` prints `-x` and
+    /// `echo -- -n` prints `-- -n`.
+    fn parse(mut args: Vec<String>) -> (EchoOptions, Vec<String>) {
+        let mut options = EchoOptions::default();
+
+        let n_options = args
+            .iter()
+            .take_while(|arg| {
+                arg.len() > 1
+                    && arg.starts_with('-')
+                    && arg[1..].chars().all(|x| matches!(x, 'n' | 'e' | 'E'))
+            })
+            .count();
+
+        for arg in args.drain(..n_options) {
+            for flag in arg[1..].chars() {
+                match flag {
+                    'n' => options.newline = false,
+                    'e' => options.escapes = true,
+                    _ => options.escapes = false,
+                }
+            }
+        }
+
+        (options, args)
+    }
+}
+
+/// The options a `set` command turns on (`Some(true)`) or off; `None` leaves one as it was.
+#[derive(Default)]
+struct SetOptions {
+    noexec: Option<bool>,
+    posix: Option<bool>,
+}
+
+struct ShoptOptions {
+    /// `Some(true)` for `-s`, `Some(false)` for `-u`; `None` just reports the options.
+    set: Option<bool>,
+    quiet: bool,
+}
+
+impl ShoptOptions {
+    fn parse(args: Vec<String>) -> anyhow::Result<(ShoptOptions, Vec<String>)> {
+        let mut options = ShoptOptions {
+            set: None,
+            quiet: false,
+        };
+
+        let mut args = args.into_iter().peekable();
+        while let Some(arg) = args.next_if(|x| x.len() > 1 && x.starts_with('-')) {
+            for flag in arg[1..].chars() {
+                let set = match flag {
+                    's' => true,
+                    'u' => false,
+                    'q' => {
+                        options.quiet = true;
+                        continue;
+                    }
+                    _ => anyhow::bail!("shopt: -{}: invalid option", flag),
+                };
+
+                if options.set.is_some_and(|x| x != set) {
+                    anyhow::bail!("shopt: cannot set and unset shell options simultaneously");
+                }
+                options.set = Some(set);
+            }
+        }
+
+        Ok((options, args.collect()))
+    }
+}
+
+struct UlimitOptions {
+    soft: bool,
+    hard: bool,
+    /// `-a`: every limit, each on a labelled line.
+    all: bool,
+    limits: Vec<&'static ulimit::Limit>,
+    /// The new limit, in the limit's own units; `RLIM_INFINITY` for `unlimited`.
+    value: Option<nix::sys::resource::rlim_t>,
+}
+
+impl UlimitOptions {
+    fn parse(args: Vec<String>) -> anyhow::Result<UlimitOptions> {
+        let mut options = UlimitOptions {
+            soft: false,
+            hard: false,
+            all: false,
+            limits: vec![],
+            value: None,
+        };
+
+        let mut args = args.into_iter().peekable();
+        while let Some(arg) = args.next_if(|x| x.len() > 1 && x.starts_with('-')) {
+            for flag in arg[1..].chars() {
+                match flag {
+                    'S' => options.soft = true,
+                    'H' => options.hard = true,
+                    'a' => options.all = true,
+                    _ => match ulimit::find(flag) {
+                        Some(limit) => options.limits.push(limit),
+                        None => anyhow::bail!("ulimit: -{}: invalid option", flag),
+                    },
+                }
+            }
+        }
+
+        options.value = match args.next() {
+            None => None,
+            Some(_) if options.all => anyhow::bail!("ulimit: -a: cannot set every limit at once"),
+            Some(value) if value == "unlimited" => Some(nix::sys::resource::RLIM_INFINITY),
+            Some(value) => match value.parse() {
+                Ok(value) => Some(value),
+                Err(_) => anyhow::bail!("ulimit: {}: invalid number", value),
+            },
+        };
+        if args.next().is_some() {
+            anyhow::bail!("ulimit: too many arguments");
+        }
+
+        if options.limits.is_empty() {
+            options.limits.push(&ulimit::LIMITS[0]);
+        }
+
+        Ok(options)
+    }
+}
+
+/// A simple command as written: its words and redirections, before anything is looked up or run.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Statement {
+    pub words: Vec<String>,
+    pub redirections: Vec<Redirection>,
+}
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum ParseError {
+    #[error("Line ended in a '\\'.")]
+    TrailingBackslash,
+    #[error("unexpected end of line while looking for matching `{0}'")]
+    UnterminatedQuote(char),
+    #[error("{0}: invalid redirection")]
+    BadRedirection(String),
+    #[error("syntax error near unexpected token `newline'")]
+    MissingRedirectionTarget,
+}
+
+/// Parses a line without running it, e.g. to highlight or check a script.
+///
+/// There are no pipelines or command separators yet, so a line holds at most one statement, and
+/// none if it's blank.
+pub fn parse_line(line: &str) -> Result<Vec<Statement>, ParseError> {
+    let mut tokens = LineTokenIter::new(line);
+    let words = tokens.by_ref().collect::<Vec<_>>();
+
+    if let Some(err) = tokens.error.take() {
+        return Err(err);
+    }
+
+    if words.is_empty() && tokens.redirections.is_empty() {
+        return Ok(vec![]);
+    }
+
+    Ok(vec![Statement {
+        words,
+        redirections: tokens.redirections()?,
+    }])
+}
+
+impl Statement {
+    fn out<'a, 'io>(
+        &self,
+        standard: &'a StandardStreams<'io>,
+    ) -> anyhow::Result<CommandOutput<'a, 'io>> {
+        let mut files = vec![];
+        // where each open descriptor writes; closing one removes it
+        let mut descriptors = BTreeMap::from([
+            (1, OutputDestination::Stdout),
+            (2, OutputDestination::Stderr),
+        ]);
+
+        // applied left to right, so `> f 2>&1` sends both streams to `f` but `2>&1 > f` doesn't
+        for redirect in &self.redirections {
+            let destination = match &redirect.target {
+                RedirectionTarget::File(path, mode) => {
+                    let mut options = OpenOptions::new();
+                    let mut options = options.create(true);
+                    options = match mode {
+                        RedirectionMode::Write => options.write(true).truncate(true),
+                        RedirectionMode::Append => options.append(true),
+                    };
+
+                    let file = options
+                        .open(path)
+                        .map_err(|err| anyhow::anyhow!("{}: {}", path, io_reason(&err)))?;
+                    files.push(RefCell::new(file));
+                    OutputDestination::File(files.len() - 1)
+                }
+                RedirectionTarget::Duplicate(fd) => match descriptors.get(fd) {
+                    Some(destination) => *destination,
+                    None => anyhow::bail!("{}: Bad file descriptor", fd),
+                },
+                RedirectionTarget::Close => OutputDestination::Closed,
+            };
+
+            let sources = match redirect.source {
+                RedirectionSource::Stdout => vec![1],
+                RedirectionSource::Stderr => vec![2],
+                RedirectionSource::Both => vec![1, 2],
+                RedirectionSource::Descriptor(fd) => vec![fd],
+            };
+            for fd in sources {
+                match destination {
+                    OutputDestination::Closed => descriptors.remove(&fd),
+                    _ => descriptors.insert(fd, destination),
+                };
+            }
+        }
+
+        Ok(CommandOutput {
+            files,
+            standard,
+            stdout: descriptors
+                .get(&1)
+                .copied()
+                .unwrap_or(OutputDestination::Closed),
+            stderr: descriptors
+                .get(&2)
+                .copied()
+                .unwrap_or(OutputDestination::Closed),
+            _not_send: Default::default(),
+        })
+    }
+}
+
+/// `errno` for writing to a descriptor that isn't open, the same on Linux and macOS.
+const EBADF: i32 = 9;
+
+/// Where one of a command's output streams ends up once its redirections are applied.
+#[derive(Clone, Copy)]
+enum OutputDestination {
+    Stdout,
+    Stderr,
+    File(usize),
+    Closed,
+}
+
+/// The shell's own output streams, which commands write to unless redirected.
+struct StandardStreams<'io> {
+    stdout: RefCell<Box<dyn Write + 'io>>,
+    stderr: RefCell<Box<dyn Write + 'io>>,
+}
+
+struct CommandOutput<'a, 'io> {
+    files: Vec<RefCell<std::fs::File>>,
+    standard: &'a StandardStreams<'io>,
+    stdout: OutputDestination,
+    stderr: OutputDestination,
+    _not_send: PhantomData<*const ()>, // since `files` can be shared between stdout and stderr, we must make this type !Send
+}
+
+impl<'io> CommandOutput<'_, 'io> {
+    fn writers(&self) -> (CommandWriter<'_, 'io>, CommandWriter<'_, 'io>) {
+        (
+            CommandWriter {
+                destination: self.stdout,
+                output: self,
+            },
+            CommandWriter {
+                destination: self.stderr,
+                output: self,
+            },
+        )
+    }
+}
+
+struct CommandWriter<'a, 'io> {
+    destination: OutputDestination,
+    output: &'a CommandOutput<'a, 'io>,
+}
+
+impl Write for CommandWriter<'_, '_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.destination {
+            OutputDestination::Stdout => self.output.standard.stdout.borrow_mut().write(buf),
+            OutputDestination::Stderr => self.output.standard.stderr.borrow_mut().write(buf),
+            OutputDestination::File(index) => self.output.files[index].borrow_mut().write(buf),
+            OutputDestination::Closed => Err(io::Error::from_raw_os_error(EBADF)),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.destination {
+            OutputDestination::Stdout => self.output.standard.stdout.borrow_mut().flush(),
+            OutputDestination::Stderr => self.output.standard.stderr.borrow_mut().flush(),
+            OutputDestination::File(index) => self.output.files[index].borrow_mut().flush(),
+            OutputDestination::Closed => Ok(()),
+        }
+    }
+}
+
+#[derive(Default)]
+struct EnvPaths {
+    paths: Vec<PathBuf>,
+}
+
+impl EnvPaths {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let var = env::var("PATH")?;
+        Ok(EnvPaths {
+            paths: var.split(':').map(PathBuf::from).collect(),
+        })
+    }
+
+    pub fn expand(&self, command: &str) -> Option<PathBuf> {
+        for path in &self.paths {
+            let full_path = path.join(command);
+            let Ok(md) = fs::metadata(&full_path) else {
+                continue;
+            };
+
+            if md.is_file() {
+                return Some(full_path);
+            }
+        }
+
+        None
+    }
+
+    /// Names of every command found in the PATH directories.
+    pub fn command_names(&self) -> BTreeSet<String> {
+        self.paths
+            .iter()
+            .filter_map(|path| fs::read_dir(path).ok())
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| fs::metadata(entry.path()).is_ok_and(|md| md.is_file()))
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect()
+    }
+}
+
+/// The working directory as the user reached it, i.e. `$PWD` with its symlinks unresolved.
+///
+/// `$PWD` is only trusted while it still names the actual working directory; otherwise this
+/// falls back to the physical path.
+fn logical_current_dir() -> io::Result<PathBuf> {
+    let physical = env::current_dir()?;
+    match env::var_os("PWD").map(PathBuf::from) {
+        Some(pwd)
+            if pwd.is_absolute()
+                && fs::canonicalize(&pwd).ok() == fs::canonicalize(&physical).ok() =>
+        {
+            Ok(pwd)
+        }
+        _ => Ok(physical),
+    }
+}
+
+/// Resolves `.` and `..` textually, without consulting the filesystem.
+fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+/// Longest input line accepted unless overridden with `SHELL_MAX_LINE`.
+const DEFAULT_MAX_LINE: u64 = 1024 * 1024;
+
+/// Reads a line of at most `limit` bytes, not counting its newline, into `line`, returning
+/// `Ok(None)` if it was longer.
+///
+/// An over-long line is read to its end and discarded, so the next call starts on a fresh line.
+fn read_line_limited(
+    input: &mut impl BufRead,
+    line: &mut String,
+    limit: u64,
+) -> io::Result<Option<usize>> {
+    let mut buf = vec![];
+    // one byte more than the limit, so that a line of exactly `limit` bytes still fits whether or
+    // not it ends in a newline
+    let n = input
+        .by_ref()
+        .take(limit.saturating_add(1))
+        .read_until(b'\n', &mut buf)?;
+
+    if n as u64 > limit && !buf.ends_with(b"\n") {
+        loop {
+            buf.clear();
+            let n = input.by_ref().take(limit).read_until(b'\n', &mut buf)?;
+            if n == 0 || buf.ends_with(b"\n") {
+                return Ok(None);
+            }
+        }
+    }
+
+    let text =
+        String::from_utf8(buf).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    line.push_str(&text);
+    Ok(Some(n))
+}
+
+/// Describes an I/O error the way the shell reports it, e.g. `No such file or directory` without
+/// the `(os error 2)` suffix.
+fn io_reason(err: &io::Error) -> String {
+    let reason = err.to_string();
+    reason
+        .split(" (os error")
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Tidies a path for display by collapsing repeated separators and dropping `.` components
+/// and any trailing slash, without touching `..`.
+fn clean_path(path: &Path) -> PathBuf {
+    let cleaned = path
+        .components()
+        .filter(|x| *x != Component::CurDir)
+        .collect::<PathBuf>();
+
+    if cleaned.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        cleaned
+    }
+}
+
+/// Fixes a one-character typo in each missing component of `path`, as `cdspell` does, returning
+/// the corrected path if it names a directory.
+fn spell_correct(path: &Path) -> Option<PathBuf> {
+    let mut corrected = PathBuf::new();
+    for component in path.components() {
+        let candidate = corrected.join(component);
+        if candidate.exists() {
+            corrected = candidate;
+            continue;
+        }
+
+        let Component::Normal(name) = component else {
+            return None;
+        };
+
+        let parent = if corrected.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            &corrected
+        };
+        let entries = fs::read_dir(parent)
+            .ok()?
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .collect::<Vec<_>>();
+
+        let fixed = suggest::closest_within(name.to_str()?, entries.iter().map(|x| x.as_str()), 1)?;
+        corrected.push(fixed);
+    }
+
+    corrected.is_dir().then_some(corrected)
+}
+
+/// What the shell should do once a line has run.
+#[derive(Debug, PartialEq, Eq)]
+pub enum LineStatus {
+    Continue,
+    /// The line ran `exit`, with this code.
+    Exit(i32),
+}
+
+/// The outcome of [`Shell::run_line`]; an error means the line couldn't be parsed.
+pub type ShellResult = Result<LineStatus, ParseError>;
+
+/// A shell session that can be driven a line at a time or left to read its own input.
+///
+/// `'io` is how long the input and output handles given to [`Shell::with_io`] are borrowed for.
+pub struct Shell<'io> {
+    paths: EnvPaths,
+    options: ShellOptions,
+    input: Box<dyn BufRead + 'io>,
+    streams: StandardStreams<'io>,
+}
+
+impl Shell<'static> {
+    pub fn new() -> Self {
+        Shell::with_options(ShellOptions::default())
+    }
+
+    /// A shell reading the process's stdin and writing to its stdout and stderr.
+    pub fn with_options(options: ShellOptions) -> Self {
+        // read a byte at a time so that input past the current line is left for the commands we run
+        let stdin = io::stdin().as_fd().try_clone_to_owned().unwrap();
+        let stdin = BufReader::with_capacity(1, File::from(stdin));
+
+        Shell::with_io(options, stdin, io::stdout(), io::stderr())
+    }
+}
+
+impl<'io> Shell<'io> {
+    /// A shell reading lines from `input` and writing to `stdout` and `stderr`, e.g. a `Cursor`
+    /// and a pair of `Vec<u8>`s in tests.
+    ///
+    /// Commands found on the PATH still inherit the process's own stdin.
+    pub fn with_io(
+        options: ShellOptions,
+        input: impl BufRead + 'io,
+        stdout: impl Write + 'io,
+        stderr: impl Write + 'io,
+    ) -> Self {
+        Shell {
+            // without a PATH only builtins can be run
+            paths: EnvPaths::from_env().unwrap_or_default(),
+            options,
+            input: Box::new(input),
+            streams: StandardStreams {
+                stdout: RefCell::new(Box::new(stdout)),
+                stderr: RefCell::new(Box::new(stderr)),
+            },
+        }
+    }
+
+    pub fn options(&self) -> &ShellOptions {
+        &self.options
+    }
+
+    pub fn options_mut(&mut self) -> &mut ShellOptions {
+        &mut self.options
+    }
+
+    /// Prints a message from the shell itself on its stderr, giving up quietly if that fails since
+    /// there's nowhere else to report it.
+    fn report(&self, message: impl fmt::Display) {
+        let _ = writeln!(self.streams.stderr.borrow_mut(), "{}", message);
+    }
+
+    /// Parses and runs a single line of input, failing only if the line can't be parsed.
+    ///
+    /// Anything that goes wrong once it's parsed, such as bad arguments to a builtin, is reported
+    /// on stderr instead.
+    pub fn run_line(&mut self, line: &str) -> ShellResult {
+        let Some(statement) = parse_line(line)?.pop() else {
+            return Ok(LineStatus::Continue);
+        };
+
+        // interactive shells ignore `-n`, or there would be no way to turn it back off
+        if self.options.noexec && !self.options.interactive {
+            return Ok(LineStatus::Continue);
+        }
+
+        let out = match statement.out(&self.streams) {
+            Ok(out) => out,
+            Err(err) => {
+                self.report(err);
+                return Ok(LineStatus::Continue);
+            }
+        };
+
+        let (mut stdout, mut stderr) = out.writers();
+
+        let mut words = statement.words.into_iter();
+        // a bare redirection such as `> out.txt` only creates the file
+        let Some(name) = words.next() else {
+            return Ok(LineStatus::Continue);
+        };
+
+        let exit = match Command::parse(name, words.collect()) {
+            Ok(command) => {
+                command.execute(&self.paths, &mut self.options, &mut stdout, &mut stderr)
+            }
+            Err(err) => {
+                // stderr may be closed, in which case there's nowhere to say what went wrong
+                let _ = writeln!(stderr, "{}", err);
+                None
+            }
+        };
+
+        Ok(exit.map_or(LineStatus::Continue, LineStatus::Exit))
+    }
+
+    /// Reads and runs lines from the input until it ends or a line exits, returning the exit
+    /// code.
+    pub fn run_interactive(&mut self) -> i32 {
+        let interactive = self.options.interactive;
+        let max_line = env::var("SHELL_MAX_LINE")
+            .ok()
+            .and_then(|x| x.parse().ok())
+            // no line at all would fit in 0 bytes
+            .filter(|&x| x > 0)
+            .unwrap_or(DEFAULT_MAX_LINE);
+
+        let mut line_number = 0;
+        loop {
+            // prompt
+            if interactive {
+                if let Ok(prompt_command) = env::var("PROMPT_COMMAND") {
+                    match self.run_line(&prompt_command) {
+                        Ok(LineStatus::Exit(code)) => return code,
+                        Ok(LineStatus::Continue) => {}
+                        Err(err) => self.report(err),
+                    }
+                }
+
+                // if stdout has gone away the prompt is lost, but input can still be run
+                let mut stdout = self.streams.stdout.borrow_mut();
+                let _ = write!(stdout, "$ ").and_then(|_| stdout.flush());
+            }
+
+            // Wait for user input
+            let mut input = String::new();
+            line_number += 1;
+            match read_line_limited(&mut self.input, &mut input, max_line) {
+                Ok(Some(0)) => return 0,
+                Ok(Some(_)) => {}
+                Ok(None) => {
+                    let message = format!("shell: line too long (more than {} bytes)", max_line);
+                    self.report(message);
+                    continue;
+                }
+                Err(err) => {
+                    let message = format!("shell: failed to read input: {}", err);
+                    self.report(message);
+                    return 1;
+                }
+            }
+
+            match self.run_line(&input) {
+                Ok(LineStatus::Exit(code)) => return code,
+                Ok(LineStatus::Continue) => {}
+                Err(err) if interactive => self.report(err),
+                Err(err) => {
+                    let message = format!("line {}: {}", line_number, err);
+                    self.report(message);
+                    // a script can't sensibly carry on past a line it failed to parse
+                    return 2;
+                }
+            }
+        }
+    }
+}
+
+impl Default for Shell<'static> {
+    fn default() -> Self {
+        Shell::new()
+    }
+}
@@ -0,0 +1,23 @@
+#[derive(Default)]
+pub struct ShellOptions {
+    pub interactive: bool,
+    /// `--posix`/`set -o posix`: stick to POSIX `sh` behaviour where it differs from ours. For now
+    /// the only difference is that `echo` takes no options, so `echo -n x` prints `-n x`.
+    pub posix: bool,
+    /// `-n`/`set -n`: parse each line but don't run it, to check a script's syntax.
+    pub noexec: bool,
+    /// `cdspell`: let an interactive `cd` fix a small typo in each directory name.
+    pub cdspell: bool,
+}
+
+impl ShellOptions {
+    /// Options that `shopt` can toggle, in the order it lists them.
+    pub const SHOPT_NAMES: &'static [&'static str] = &["cdspell"];
+
+    pub fn shopt(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "cdspell" => Some(&mut self.cdspell),
+            _ => None,
+        }
+    }
+}
@@ -1,70 +1,71 @@
 use peeking_take_while::PeekableExt;
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum RedirectionMode {
     Write,
     Append,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum RedirectionSource {
     Stdout,
     Stderr,
     Both,
+    /// Any other descriptor, e.g. `3>` or `0>`; commands never write to these, but they can be
+    /// duplicated onto stdout or stderr.
+    Descriptor(u32),
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
+pub enum RedirectionTarget {
+    File(String, RedirectionMode),
+    /// `>&N`: write wherever descriptor N currently writes.
+    Duplicate(u32),
+    /// `>&-`
+    Close,
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Redirection {
     pub source: RedirectionSource,
-    pub mode: RedirectionMode,
-    pub target: String,
+    pub target: RedirectionTarget,
 }
 
 impl Redirection {
-    pub fn parse(value: &str) -> Option<Redirection> {
-        if value.len() == 0 {
-            return None;
-        }
-
-        let mut chars = value.chars().peekable();
-        let mut source = RedirectionSource::Stdout;
+    /// Builds a redirection from its operator, such as `2>>` or `>&`, and the word after it.
+    ///
+    /// The target is taken as it is, so a quoted target like `'&2'` is always a file name.
+    pub fn parse(operator: &str, target: String) -> Option<Redirection> {
+        let mut chars = operator.chars().peekable();
 
-        if *chars.peek().unwrap() == '&' {
-            source = RedirectionSource::Both;
-            chars.next().unwrap();
+        let source = if chars.next_if_eq(&'&').is_some() {
+            RedirectionSource::Both
         } else {
             let n_str = chars
                 .by_ref()
                 .peeking_take_while(|x| x.is_ascii_digit())
                 .collect::<String>();
 
-            if n_str.len() > 0 {
-                let n = n_str.parse::<u32>().unwrap();
-                if n == 0 || n == 1 {
-                    // do nothing
-                } else if n == 2 {
-                    source = RedirectionSource::Stderr;
-                } else {
-                    return None;
-                }
+            match n_str.parse::<u32>() {
+                _ if n_str.is_empty() => RedirectionSource::Stdout,
+                Ok(1) => RedirectionSource::Stdout,
+                Ok(2) => RedirectionSource::Stderr,
+                Ok(n) => RedirectionSource::Descriptor(n),
+                Err(_) => return None,
             }
-        }
-
-        let n_lt = chars.by_ref().take_while(|x| *x == '>').count();
+        };
 
-        let mode = match n_lt {
-            1 => RedirectionMode::Write,
-            2 => RedirectionMode::Append,
+        let target = match (chars.collect::<String>().as_ref(), &source) {
+            (">", _) => RedirectionTarget::File(target, RedirectionMode::Write),
+            (">>", _) => RedirectionTarget::File(target, RedirectionMode::Append),
+            (">&", RedirectionSource::Both) => return None,
+            (">&", _) if target == "-" => RedirectionTarget::Close,
+            (">&", _) if !target.is_empty() && target.chars().all(|x| x.is_ascii_digit()) => {
+                RedirectionTarget::Duplicate(target.parse().ok()?)
+            }
             _ => return None,
         };
 
-        Some(Redirection {
-            source,
-            mode,
-            target: chars
-                .skip_while(|x| x.is_whitespace())
-                .take_while(|x| !x.is_whitespace())
-                .collect(),
-        })
+        Some(Redirection { source, target })
     }
 }
@@ -0,0 +1,108 @@
+/// Furthest a candidate may be from the typed name and still be suggested.
+const MAX_DISTANCE: usize = 2;
+
+/// Picks the candidate closest to `name`, if exactly one is closest and it is near enough to be
+/// a plausible typo.
+pub fn closest<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    closest_within(name, candidates, MAX_DISTANCE)
+}
+
+/// Like [`closest`], but with a caller-chosen limit on how far off a candidate may be.
+pub fn closest_within<'a>(
+    name: &str,
+    candidates: impl Iterator<Item = &'a str>,
+    max_distance: usize,
+) -> Option<&'a str> {
+    let mut best: Option<(usize, &str)> = None;
+    let mut tied = false;
+
+    for candidate in candidates {
+        let distance = edit_distance(name, candidate);
+        if distance > max_distance {
+            continue;
+        }
+
+        match best {
+            Some((best_distance, _)) if distance > best_distance => {}
+            Some((best_distance, best_candidate))
+                if distance == best_distance && candidate != best_candidate =>
+            {
+                tied = true
+            }
+            _ => {
+                tied = false;
+                best = Some((distance, candidate));
+            }
+        }
+    }
+
+    match best {
+        Some((_, candidate)) if !tied => Some(candidate),
+        _ => None,
+    }
+}
+
+/// Edit distance where swapping two adjacent characters counts as a single edit, since that's
+/// the most common typo (`ehco` for `echo`).
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+
+    // distances[i][j] is the distance between the first i chars of `a` and the first j of `b`
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    distances[0] = (0..=b.len()).collect();
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+
+            distances[i][j] = distance;
+        }
+    }
+
+    distances[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transposition_is_one_edit() {
+        assert_eq!(edit_distance("ehco", "echo"), 1);
+        assert_eq!(edit_distance("echo", "echo"), 0);
+        assert_eq!(edit_distance("ech", "echo"), 1);
+        assert_eq!(edit_distance("", "cd"), 2);
+    }
+
+    #[test]
+    fn closest_picks_the_single_best_candidate() {
+        let candidates = ["echo", "exit", "type"];
+
+        assert_eq!(closest("ehco", candidates.into_iter()), Some("echo"));
+        assert_eq!(closest("exti", candidates.into_iter()), Some("exit"));
+        assert_eq!(closest("xxxxxx", candidates.into_iter()), None);
+    }
+
+    #[test]
+    fn ties_suggest_nothing() {
+        assert_eq!(closest("ca", ["cd", "cat"].into_iter()), None);
+        // the same name showing up twice (e.g. in two PATH entries) isn't a tie
+        assert_eq!(closest("ca", ["cat", "cat"].into_iter()), Some("cat"));
+        // a closer candidate breaks an earlier tie
+        assert_eq!(
+            closest("pwdx", ["pwa", "pwb", "pwd"].into_iter()),
+            Some("pwd")
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use std::io;
+
+use nix::sys::resource::{self, rlim_t, Resource, RLIM_INFINITY};
+
+/// A resource limit that `ulimit` can show or change.
+pub struct Limit {
+    pub flag: char,
+    pub description: &'static str,
+    /// What values are counted in, if not just a number of things.
+    unit: Option<&'static str>,
+    /// How many of the kernel's units (bytes) make one of ours.
+    scale: rlim_t,
+    resource: Resource,
+}
+
+/// The limits `ulimit` knows, in the order `ulimit -a` lists them. The first is the default when
+/// no limit is named, as in bash.
+pub const LIMITS: &[Limit] = &[
+    Limit {
+        flag: 'f',
+        description: "file size",
+        unit: Some("blocks"),
+        scale: 1024,
+        resource: Resource::RLIMIT_FSIZE,
+    },
+    Limit {
+        flag: 'n',
+        description: "open files",
+        unit: None,
+        scale: 1,
+        resource: Resource::RLIMIT_NOFILE,
+    },
+    Limit {
+        flag: 's',
+        description: "stack size",
+        unit: Some("kbytes"),
+        scale: 1024,
+        resource: Resource::RLIMIT_STACK,
+    },
+    Limit {
+        flag: 'u',
+        description: "max user processes",
+        unit: None,
+        scale: 1,
+        resource: Resource::RLIMIT_NPROC,
+    },
+];
+
+pub fn find(flag: char) -> Option<&'static Limit> {
+    LIMITS.iter().find(|x| x.flag == flag)
+}
+
+impl Limit {
+    /// The start of this limit's line in `ulimit -a`, lined up like bash's.
+    pub fn label(&self) -> String {
+        let option = match self.unit {
+            Some(unit) => format!("({}, -{})", unit, self.flag),
+            None => format!("(-{})", self.flag),
+        };
+        format!("{:<24}{:>12} ", self.description, option)
+    }
+
+    /// The current soft or hard limit as `ulimit` prints it.
+    pub fn show(&self, hard: bool) -> io::Result<String> {
+        let (soft_limit, hard_limit) = resource::getrlimit(self.resource)?;
+        let value = if hard { hard_limit } else { soft_limit };
+
+        Ok(if value == RLIM_INFINITY {
+            "unlimited".to_string()
+        } else {
+            (value / self.scale).to_string()
+        })
+    }
+
+    /// Sets the soft limit, the hard limit or both to `value`, in this limit's units.
+    /// `RLIM_INFINITY` removes the limit.
+    pub fn set(&self, value: rlim_t, soft: bool, hard: bool) -> io::Result<()> {
+        let value = if value == RLIM_INFINITY {
+            value
+        } else {
+            value
+                .checked_mul(self.scale)
+                .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?
+        };
+
+        // the kernel rejects a soft limit above the hard one, which is the error users see
+        let (mut soft_limit, mut hard_limit) = resource::getrlimit(self.resource)?;
+        if soft {
+            soft_limit = value;
+        }
+        if hard {
+            hard_limit = value;
+        }
+
+        Ok(resource::setrlimit(self.resource, soft_limit, hard_limit)?)
+    }
+}
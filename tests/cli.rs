@@ -0,0 +1,195 @@
+use std::{
+    env, fs,
+    io::Write,
+    os::unix,
+    path::PathBuf,
+    process::{self, Command, Output, Stdio},
+};
+
+/// Runs the shell binary with `args`, `input` piped to its stdin and `envs` added to its
+/// environment.
+fn run_shell(args: &[&str], input: &str, envs: &[(&str, &str)]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_codecrafters-shell"))
+        .args(args)
+        .envs(envs.iter().copied())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// An empty directory for one test, with any symlinks in the temp dir's own path resolved.
+fn test_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("shell-cli-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::canonicalize(dir).unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn over_long_lines_are_skipped() {
+    let output = run_shell(
+        &[],
+        "echo 12345678\necho ok\necho ok",
+        &[("SHELL_MAX_LINE", "7")],
+    );
+
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "ok\nok\n");
+    assert_eq!(
+        stderr(&output),
+        "shell: line too long (more than 7 bytes)\n"
+    );
+}
+
+#[test]
+fn zero_line_limit_falls_back_to_default() {
+    let output = run_shell(&[], "echo ok\n", &[("SHELL_MAX_LINE", "0")]);
+
+    assert_eq!(stdout(&output), "ok\n");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn piped_input_shows_no_prompt() {
+    let output = run_shell(&[], "echo one\necho two\n", &[]);
+
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "one\ntwo\n");
+}
+
+#[test]
+fn interactive_flag_shows_prompts_on_piped_input() {
+    let output = run_shell(&["-i"], "echo one\n", &[]);
+
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "$ one\n$ ");
+}
+
+#[test]
+fn unknown_flag_prints_usage() {
+    let output = run_shell(&["-x"], "echo never\n", &[]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(stdout(&output), "");
+    assert_eq!(
+        stderr(&output),
+        "-x: invalid option\n\
+         usage: shell [-i] [-n] [--posix] [--version] [--norc] [--noprofile]\n"
+    );
+}
+
+#[test]
+fn version_flag_prints_the_version() {
+    let output = run_shell(&["--version"], "echo never\n", &[]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        format!("codecrafters-shell {}\n", env!("CARGO_PKG_VERSION"))
+    );
+    assert_eq!(stderr(&output), "");
+}
+
+#[test]
+fn logical_and_physical_cd_up_through_a_symlink() {
+    let dir = test_dir("cd-symlink");
+    fs::create_dir_all(dir.join("real/nested")).unwrap();
+    unix::fs::symlink(dir.join("real/nested"), dir.join("link")).unwrap();
+
+    let script = format!(
+        "cd {dir}/link\npwd\ncd ..\npwd\ncd {dir}/link\ncd -P ..\npwd\n",
+        dir = dir.display()
+    );
+    let output = run_shell(&[], &script, &[]);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(stderr(&output), "");
+    assert_eq!(
+        stdout(&output),
+        format!("{dir}/link\n{dir}\n{dir}/real\n", dir = dir.display())
+    );
+}
+
+#[test]
+fn cd_errors_show_a_cleaned_path() {
+    let dir = test_dir("cd-messages");
+    fs::create_dir(dir.join("existing")).unwrap();
+
+    let script = format!(
+        "cd {}\ncd nonexistent/\ncd ./nonexistent\ncd a//b\ncd existing/\npwd\n",
+        dir.display()
+    );
+    let output = run_shell(&[], &script, &[]);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(
+        stderr(&output),
+        "cd: nonexistent: No such file or directory\n\
+         cd: nonexistent: No such file or directory\n\
+         cd: a/b: No such file or directory\n"
+    );
+    assert_eq!(stdout(&output), format!("{}/existing\n", dir.display()));
+}
+
+#[test]
+fn cd_into_a_dangling_symlink_fails_and_stays_put() {
+    let dir = test_dir("cd-dangling");
+    unix::fs::symlink(dir.join("missing"), dir.join("dangling")).unwrap();
+
+    let script = format!("cd {}\ncd dangling\npwd\n", dir.display());
+    let output = run_shell(&[], &script, &[]);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(stderr(&output), "cd: dangling: No such file or directory\n");
+    assert_eq!(stdout(&output), format!("{}\n", dir.display()));
+}
+
+#[test]
+fn commands_read_the_rest_of_piped_input() {
+    let output = run_shell(&[], "cat\nhello\n", &[]);
+
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "hello\n");
+}
+
+#[test]
+fn cdspell_fixes_a_typo_when_interactive() {
+    let dir = test_dir("cdspell");
+    fs::create_dir(dir.join("projects")).unwrap();
+
+    let script = format!("cd {}\nshopt -s cdspell\ncd projcts\npwd\n", dir.display());
+    let output = run_shell(&["-i"], &script, &[]);
+    let unspelled = run_shell(&[], &script, &[]);
+    fs::remove_dir_all(&dir).unwrap();
+
+    let fixed = dir.join("projects");
+    assert_eq!(stderr(&output), "");
+    assert_eq!(
+        stdout(&output),
+        format!("$ $ $ {}\n$ {}\n$ ", fixed.display(), fixed.display())
+    );
+    // scripts don't get their typos fixed
+    assert_eq!(
+        stderr(&unspelled),
+        "cd: projcts: No such file or directory\n"
+    );
+    assert_eq!(stdout(&unspelled), format!("{}\n", dir.display()));
+}
@@ -0,0 +1,127 @@
+use codecrafters_shell::{
+    parse_line, ParseError, Redirection, RedirectionMode, RedirectionSource, RedirectionTarget,
+    Statement,
+};
+
+#[test]
+fn statement_with_redirections() {
+    let statements = parse_line("echo 'hello  there' 2>>log.txt >&2").unwrap();
+
+    assert_eq!(
+        statements,
+        vec![Statement {
+            words: vec!["echo".to_string(), "hello  there".to_string()],
+            redirections: vec![
+                Redirection {
+                    source: RedirectionSource::Stderr,
+                    target: RedirectionTarget::File("log.txt".to_string(), RedirectionMode::Append),
+                },
+                Redirection {
+                    source: RedirectionSource::Stdout,
+                    target: RedirectionTarget::Duplicate(2),
+                },
+            ],
+        }]
+    );
+}
+
+#[test]
+fn blank_line_has_no_statements() {
+    assert_eq!(parse_line("  \n").unwrap(), vec![]);
+}
+
+#[test]
+fn unterminated_quotes() {
+    assert_eq!(
+        parse_line("echo \"unterminated"),
+        Err(ParseError::UnterminatedQuote('"'))
+    );
+    assert_eq!(
+        parse_line("echo 'unterminated"),
+        Err(ParseError::UnterminatedQuote('\''))
+    );
+    assert_eq!(
+        parse_line("echo $'unterminated"),
+        Err(ParseError::UnterminatedQuote('\''))
+    );
+}
+
+#[test]
+fn trailing_backslash() {
+    assert_eq!(parse_line("echo a\\"), Err(ParseError::TrailingBackslash));
+}
+
+#[test]
+fn bad_redirection() {
+    assert_eq!(
+        parse_line("echo hi >>&2"),
+        Err(ParseError::BadRedirection(">>&2".to_string()))
+    );
+}
+
+#[test]
+fn redirection_without_target() {
+    assert_eq!(
+        parse_line("echo hi >"),
+        Err(ParseError::MissingRedirectionTarget)
+    );
+    assert_eq!(
+        parse_line("echo hi 2>> \n"),
+        Err(ParseError::MissingRedirectionTarget)
+    );
+}
+
+#[test]
+fn redirection_before_command() {
+    let statements = parse_line("> out.txt echo hi").unwrap();
+
+    assert_eq!(
+        statements,
+        vec![Statement {
+            words: vec!["echo".to_string(), "hi".to_string()],
+            redirections: vec![Redirection {
+                source: RedirectionSource::Stdout,
+                target: RedirectionTarget::File("out.txt".to_string(), RedirectionMode::Write),
+            }],
+        }]
+    );
+}
+
+#[test]
+fn quoted_redirection_targets_are_file_names() {
+    let redirections = |line| parse_line(line).unwrap()[0].redirections.clone();
+
+    assert_eq!(
+        redirections("echo hi > '&2'"),
+        vec![Redirection {
+            source: RedirectionSource::Stdout,
+            target: RedirectionTarget::File("&2".to_string(), RedirectionMode::Write),
+        }]
+    );
+    assert_eq!(
+        redirections("echo hi > '>x'"),
+        vec![Redirection {
+            source: RedirectionSource::Stdout,
+            target: RedirectionTarget::File(">x".to_string(), RedirectionMode::Write),
+        }]
+    );
+}
+
+#[test]
+fn other_descriptors() {
+    let redirections = |line| parse_line(line).unwrap()[0].redirections.clone();
+
+    assert_eq!(
+        redirections("echo hi 0>f 3>&1"),
+        vec![
+            Redirection {
+                source: RedirectionSource::Descriptor(0),
+                target: RedirectionTarget::File("f".to_string(), RedirectionMode::Write),
+            },
+            Redirection {
+                source: RedirectionSource::Descriptor(3),
+                target: RedirectionTarget::Duplicate(1),
+            },
+        ]
+    );
+}
@@ -0,0 +1,464 @@
+use std::{
+    env, fs,
+    io::{self, Cursor, Write},
+    process,
+};
+
+use codecrafters_shell::{LineStatus, Shell, ShellOptions};
+
+/// Runs `script` as a non-interactive shell would, returning its exit code, stdout and stderr.
+fn run_script(script: &str) -> (i32, String, String) {
+    let (mut stdout, mut stderr) = (vec![], vec![]);
+    let code = Shell::with_io(
+        ShellOptions::default(),
+        Cursor::new(script),
+        &mut stdout,
+        &mut stderr,
+    )
+    .run_interactive();
+
+    (
+        code,
+        String::from_utf8(stdout).unwrap(),
+        String::from_utf8(stderr).unwrap(),
+    )
+}
+
+#[test]
+fn run_line_captures_output_and_exit() {
+    let (mut stdout, mut stderr) = (vec![], vec![]);
+    let mut shell = Shell::with_io(
+        ShellOptions::default(),
+        io::empty(),
+        &mut stdout,
+        &mut stderr,
+    );
+
+    assert_eq!(shell.run_line("echo hello").unwrap(), LineStatus::Continue);
+    assert_eq!(shell.run_line("type echo").unwrap(), LineStatus::Continue);
+    assert_eq!(
+        shell.run_line("nosuchcommand-xyz").unwrap(),
+        LineStatus::Continue
+    );
+    assert_eq!(shell.run_line("exit 3").unwrap(), LineStatus::Exit(3));
+    drop(shell);
+
+    assert_eq!(
+        String::from_utf8(stdout).unwrap(),
+        "hello\necho is a shell builtin\n"
+    );
+    assert_eq!(
+        String::from_utf8(stderr).unwrap(),
+        "nosuchcommand-xyz: command not found\n"
+    );
+}
+
+#[test]
+fn only_interactive_shells_suggest_commands() {
+    let (_, _, stderr) = run_script("ehco hi\n");
+    assert_eq!(stderr, "ehco: command not found\n");
+
+    let mut stderr = vec![];
+    let options = ShellOptions {
+        interactive: true,
+        ..Default::default()
+    };
+    let mut shell = Shell::with_io(options, io::empty(), io::sink(), &mut stderr);
+    shell.run_line("ehco hi").unwrap();
+    drop(shell);
+
+    assert_eq!(
+        String::from_utf8(stderr).unwrap(),
+        "ehco: command not found. Did you mean 'echo'?\n"
+    );
+}
+
+#[test]
+fn run_line_ignores_blank_lines() {
+    let mut stdout = vec![];
+    let mut shell = Shell::with_io(
+        ShellOptions::default(),
+        io::empty(),
+        &mut stdout,
+        io::sink(),
+    );
+
+    assert_eq!(shell.run_line("").unwrap(), LineStatus::Continue);
+    assert_eq!(shell.run_line("   \n").unwrap(), LineStatus::Continue);
+    drop(shell);
+
+    assert!(stdout.is_empty());
+}
+
+#[test]
+fn script_reads_from_input_and_writes_to_buffers() {
+    let (code, stdout, stderr) =
+        run_script("echo a\nnosuchcommand-xyz\necho b >&2\nexit 3\necho never\n");
+
+    assert_eq!(code, 3);
+    assert_eq!(stdout, "a\n");
+    assert_eq!(stderr, "nosuchcommand-xyz: command not found\nb\n");
+}
+
+#[test]
+fn script_exits_zero_at_end_of_input() {
+    assert_eq!(run_script("echo a"), (0, "a\n".to_string(), String::new()));
+}
+
+#[test]
+fn builtin_usage_errors_do_not_end_a_script() {
+    let (code, stdout, stderr) = run_script("cd a b\npwd x\nshopt -z\necho still\n");
+
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "still\n");
+    assert_eq!(stderr.lines().count(), 3);
+}
+
+#[test]
+fn parse_errors_end_a_script() {
+    let (code, stdout, stderr) = run_script("echo 'open\necho never\n");
+
+    assert_eq!(code, 2);
+    assert_eq!(stdout, "");
+    assert!(stderr.contains("looking for matching"));
+}
+
+#[test]
+fn noexec_checks_syntax_without_running_anything() {
+    let path = env::temp_dir().join(format!("shell-test-noexec-{}", process::id()));
+    let script = format!("echo a > {}\nexit 3\necho 'bad\necho b\n", path.display());
+
+    let (mut stdout, mut stderr) = (vec![], vec![]);
+    let options = ShellOptions {
+        noexec: true,
+        ..Default::default()
+    };
+    let code =
+        Shell::with_io(options, Cursor::new(script), &mut stdout, &mut stderr).run_interactive();
+
+    assert_eq!(code, 2);
+    assert!(stdout.is_empty());
+    assert!(String::from_utf8(stderr).unwrap().starts_with("line 3: "));
+    assert!(!path.exists());
+}
+
+#[test]
+fn set_rejects_options_it_does_not_implement() {
+    let (code, stdout, stderr) = run_script(
+        "set -e\nset -o pipefail\nset -o\nset -z\necho ran\nset -o noexec\necho skipped\n",
+    );
+
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "ran\n");
+    assert_eq!(
+        stderr,
+        "set: -e: not supported\nset: -o pipefail: not supported\n\
+         set: -o: option requires an argument\nset: -z: invalid option\n"
+    );
+}
+
+#[test]
+fn shopt_lists_options_by_state() {
+    let script = "shopt\nshopt -s\nshopt -u\nshopt -s cdspell\nshopt cdspell\nshopt -s\nshopt -u\n\
+                  shopt -q\nshopt -q cdspell\nshopt nope\n";
+    let (off, on) = ("cdspell        \toff\n", "cdspell        \ton\n");
+
+    let (code, stdout, stderr) = run_script(script);
+
+    assert_eq!(code, 0);
+    assert_eq!(stdout, [off, off, on, on].concat());
+    assert_eq!(stderr, "shopt: nope: invalid shell option name\n");
+}
+
+#[test]
+fn ulimit_round_trips_the_open_file_limit() {
+    let (_, limit, _) = run_script("ulimit -n\n");
+    let limit = limit.trim_end().parse::<u64>().unwrap();
+
+    assert_eq!(
+        run_script(&format!("ulimit -S -n {}\nulimit -S -n\n", limit)),
+        (0, format!("{}\n", limit), String::new())
+    );
+}
+
+#[test]
+fn ulimit_cannot_exceed_the_hard_limit() {
+    let (_, hard, _) = run_script("ulimit -H -n\n");
+    let hard = hard.trim_end().parse::<u64>().unwrap();
+
+    assert_eq!(
+        run_script(&format!("ulimit -S -n {}\n", hard + 1)),
+        (
+            0,
+            String::new(),
+            "ulimit: open files: cannot modify limit: Invalid argument\n".to_string()
+        )
+    );
+}
+
+#[test]
+fn ulimit_all_labels_each_limit() {
+    let (code, stdout, stderr) = run_script("ulimit -a\n");
+
+    assert_eq!((code, stderr.as_str()), (0, ""));
+    assert_eq!(stdout.lines().count(), 4);
+    assert!(stdout.contains("\nopen files                      (-n) "));
+}
+
+#[test]
+fn large_binary_output_is_copied_intact() {
+    // a few MiB of every byte value, so nothing can be lost to text decoding or short writes
+    let mut state = 0x2545_f491_u32;
+    let data = (0..3 * 1024 * 1024)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect::<Vec<_>>();
+    let path = env::temp_dir().join(format!("shell-test-binary-{}", process::id()));
+    fs::write(&path, &data).unwrap();
+
+    let (mut stdout, mut stderr) = (vec![], vec![]);
+    let mut shell = Shell::with_io(
+        ShellOptions::default(),
+        io::empty(),
+        &mut stdout,
+        &mut stderr,
+    );
+    shell.run_line(&format!("cat {}", path.display())).unwrap();
+    drop(shell);
+    fs::remove_file(&path).unwrap();
+
+    assert!(stderr.is_empty());
+    assert_eq!(stdout.len(), data.len());
+    assert!(stdout == data);
+}
+
+#[test]
+fn stdout_duplicated_onto_stderr() {
+    assert_eq!(
+        run_script("echo hi 1>&2\n"),
+        (0, String::new(), "hi\n".to_string())
+    );
+}
+
+#[test]
+fn closed_stderr_drops_errors() {
+    assert_eq!(
+        run_script("nosuchcommand-xyz 2>&-\necho hi 2>&-\n"),
+        (0, "hi\n".to_string(), String::new())
+    );
+}
+
+#[test]
+fn writing_to_closed_stdout_fails() {
+    assert_eq!(
+        run_script("echo hi >&-\n"),
+        (
+            0,
+            String::new(),
+            "echo: write error: Bad file descriptor\n".to_string()
+        )
+    );
+}
+
+#[test]
+fn duplicating_an_unopened_descriptor_fails() {
+    assert_eq!(
+        run_script("echo hi >&3\necho hi 3>&-\necho hi >&3\n"),
+        (
+            0,
+            "hi\n".to_string(),
+            "3: Bad file descriptor\n3: Bad file descriptor\n".to_string()
+        )
+    );
+}
+
+#[test]
+fn other_descriptors_can_be_opened_and_duplicated() {
+    let dir = env::temp_dir().join(format!("shell-test-descriptors-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let script = format!(
+        "echo a 3>{dir}/three\necho b 0>{dir}/zero\necho c 3>{dir}/dup 1>&3\n",
+        dir = dir.display()
+    );
+
+    let (code, stdout, stderr) = run_script(&script);
+    let read = |name: &str| fs::read_to_string(dir.join(name)).unwrap();
+    let (three, zero, dup) = (read("three"), read("zero"), read("dup"));
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(
+        (code, stdout, stderr),
+        (0, "a\nb\n".to_string(), String::new())
+    );
+    assert_eq!(
+        (three.as_str(), zero.as_str(), dup.as_str()),
+        ("", "", "c\n")
+    );
+}
+
+#[test]
+fn redirection_before_command_and_bare_redirection() {
+    let path = env::temp_dir().join(format!("shell-test-bare-redirect-{}", process::id()));
+    let script = format!(
+        "> {path} echo hi\ncat {path}\n> {path}\ncat {path}\necho done\n",
+        path = path.display()
+    );
+
+    let (code, stdout, stderr) = run_script(&script);
+    let contents = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        (code, stdout, stderr),
+        (0, "hi\ndone\n".to_string(), String::new())
+    );
+    assert_eq!(contents, "");
+}
+
+#[test]
+fn redirection_without_target_is_a_parse_error() {
+    assert_eq!(
+        run_script("echo hi >\necho never\n"),
+        (
+            2,
+            String::new(),
+            "line 1: syntax error near unexpected token `newline'\n".to_string()
+        )
+    );
+}
+
+#[test]
+fn stderr_append_keeps_earlier_output() {
+    let path = env::temp_dir().join(format!("shell-test-append-{}", process::id()));
+    let script = format!(
+        "nosuchcommand-xyz 2>> {path}\nnosuchcommand-xyz 2>>{path}\n",
+        path = path.display()
+    );
+
+    let (code, stdout, stderr) = run_script(&script);
+    let contents = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!((code, stdout, stderr), (0, String::new(), String::new()));
+    assert_eq!(contents, "nosuchcommand-xyz: command not found\n".repeat(2));
+}
+
+#[test]
+fn single_quoted_trailing_backslash_is_literal() {
+    assert_eq!(
+        run_script("echo 'a\\'\necho 'path\\' x\n"),
+        (0, "a\\\npath\\ x\n".to_string(), String::new())
+    );
+}
+
+#[test]
+fn exit_code_wraps_to_a_byte() {
+    let mut shell = Shell::with_io(ShellOptions::default(), io::empty(), io::sink(), io::sink());
+
+    assert_eq!(shell.run_line("exit 256").unwrap(), LineStatus::Exit(0));
+    assert_eq!(shell.run_line("exit 257").unwrap(), LineStatus::Exit(1));
+    assert_eq!(shell.run_line("exit -1").unwrap(), LineStatus::Exit(255));
+}
+
+#[test]
+fn echo_without_words() {
+    assert_eq!(
+        run_script("echo\necho -n\necho \"\"\necho -n x\n"),
+        (0, "\n\nx".to_string(), String::new())
+    );
+}
+
+#[test]
+fn echo_stops_option_scanning_at_first_non_option() {
+    assert_eq!(
+        run_script("echo -n -x\necho\necho -- -n\necho -nE -e x\n"),
+        (0, "-x\n-- -n\nx".to_string(), String::new())
+    );
+}
+
+#[test]
+fn posix_echo_takes_no_options() {
+    let (mut stdout, mut stderr) = (vec![], vec![]);
+    let options = ShellOptions {
+        posix: true,
+        ..Default::default()
+    };
+    let code = Shell::with_io(
+        options,
+        Cursor::new("echo -n x\n"),
+        &mut stdout,
+        &mut stderr,
+    )
+    .run_interactive();
+
+    assert_eq!(code, 0);
+    assert_eq!(String::from_utf8(stdout).unwrap(), "-n x\n");
+    assert!(stderr.is_empty());
+}
+
+#[test]
+fn set_o_posix_toggles_posix_mode() {
+    assert_eq!(
+        run_script("set -o posix\necho -n x\nset +o posix\necho -n y\n"),
+        (0, "-n x\ny".to_string(), String::new())
+    );
+}
+
+#[test]
+fn quoted_spaces_are_kept() {
+    assert_eq!(
+        run_script("echo \"a  b\" c\necho '  lead' x\"  \"y\n"),
+        (0, "a  b c\n  lead x  y\n".to_string(), String::new())
+    );
+}
+
+#[test]
+fn unicode_and_hex_escapes() {
+    assert_eq!(
+        run_script("echo $'\\u2764' $'\\x41' $'\\U0001F600'\necho -e '\\u2764\\x41'\n"),
+        (
+            0,
+            "\u{2764} A \u{1F600}\n\u{2764}A\n".to_string(),
+            String::new()
+        )
+    );
+}
+
+#[test]
+fn tabs_separate_words() {
+    assert_eq!(
+        run_script("echo\ta\t\tb \t c\necho 'x\ty'\n"),
+        (0, "a b c\nx\ty\n".to_string(), String::new())
+    );
+}
+
+/// A writer whose reader has gone away, like stdout piped into `head`.
+struct BrokenPipe;
+
+impl Write for BrokenPipe {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::ErrorKind::BrokenPipe.into())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Err(io::ErrorKind::BrokenPipe.into())
+    }
+}
+
+#[test]
+fn failing_output_does_not_panic() {
+    let options = ShellOptions {
+        interactive: true,
+        ..Default::default()
+    };
+    let script = "echo hi\nnosuchcommand-xyz\necho 'open\necho hi >\n";
+
+    let code =
+        Shell::with_io(options, Cursor::new(script), BrokenPipe, BrokenPipe).run_interactive();
+
+    assert_eq!(code, 0);
+}